@@ -1,19 +1,33 @@
 use sqlx::postgres::PgPoolOptions;
 use fake::{Fake};
 use fake::faker::name::en::*;
-use rand::Rng;
+use fake::faker::internet::en::SafeEmail;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rand::prelude::SliceRandom;
 use chrono::{NaiveDate, NaiveDateTime, Duration};
 use bigdecimal::BigDecimal;
+use std::error::Error;
+use std::sync::Arc;
 use std::time::Instant;
 use std::str::FromStr;
 use dotenv::dotenv;
 use std::env;
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinSet;
+use tokio_postgres::types::Type;
+use tokio_postgres::NoTls;
+
+mod config;
+mod tls;
+use config::GenConfig;
+use tls::TlsConfig;
+
+const DEFAULT_POOL_SIZE: u32 = 5;
 
 #[derive(Debug)]
 struct Invoice {
     customer_id: i32,
-    customer_name: String,
     invoice_date: NaiveDateTime,
     due_date: NaiveDateTime,
     total_amount: BigDecimal,
@@ -21,6 +35,77 @@ struct Invoice {
     status: String,
 }
 
+/// Which code path is used to get generated rows into Postgres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertMode {
+    /// `INSERT ... SELECT * FROM UNNEST(...)` via sqlx's `query!` macro.
+    Unnest,
+    /// `COPY ... FROM STDIN (FORMAT binary)` via tokio-postgres.
+    Copy,
+    /// A runtime-built `INSERT ... VALUES (...),(...),...` via `sqlx::query`.
+    Values,
+}
+
+impl InsertMode {
+    fn parse(raw: &str) -> Self {
+        match raw {
+            "copy" => InsertMode::Copy,
+            "unnest" => InsertMode::Unnest,
+            "values" => InsertMode::Values,
+            other => panic!("Unknown --mode value '{}'; expected 'unnest', 'copy', or 'values'", other),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            InsertMode::Unnest => "unnest",
+            InsertMode::Copy => "copy",
+            InsertMode::Values => "values",
+        }
+    }
+}
+
+/// A worker's dedicated connection to Postgres. `Copy` mode can't share a single
+/// `tokio_postgres::Client` across concurrent `COPY` calls, so each worker gets its own.
+enum WorkerConn {
+    Pool(sqlx::Pool<sqlx::Postgres>),
+    Copy(tokio_postgres::Client),
+}
+
+async fn insert_batch(mode: InsertMode, conn: &WorkerConn, invoices: &[Invoice]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match (mode, conn) {
+        (InsertMode::Unnest, WorkerConn::Pool(pool)) => insert_invoices(pool, invoices).await.map_err(Into::into),
+        (InsertMode::Values, WorkerConn::Pool(pool)) => insert_invoices_values(pool, invoices).await.map_err(Into::into),
+        (InsertMode::Copy, WorkerConn::Copy(client)) => insert_invoices_copy(client, invoices).await.map_err(Into::into),
+        _ => unreachable!("a worker's connection kind always matches the mode it was built for"),
+    }
+}
+
+/// Opens a standalone tokio-postgres connection and drives its connection future in the
+/// background, returning the client half for issuing `COPY` commands.
+async fn connect_copy_client(database_url: &str, tls: &TlsConfig) -> Result<tokio_postgres::Client, Box<dyn Error + Send + Sync>> {
+    match tls::tokio_postgres_connector(tls)? {
+        Some(connector) => {
+            let (client, connection) = tokio_postgres::connect(database_url, connector).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("copy connection error: {}", e);
+                }
+            });
+            Ok(client)
+        }
+        None => {
+            let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    eprintln!("copy connection error: {}", e);
+                }
+            });
+            Ok(client)
+        }
+    }
+}
+
 fn random_date_in_range(rng: &mut impl Rng, start: NaiveDate, end: NaiveDate) -> NaiveDate {
     let days_in_range = (end - start).num_days();
     start + Duration::days(rng.gen_range(0..=days_in_range))
@@ -30,8 +115,7 @@ async fn create_invoices_table(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<(),
     sqlx::query!(
         r#"
         CREATE TABLE IF NOT EXISTS invoices (
-            customer_id INT,
-            customer_name TEXT,
+            customer_id INT REFERENCES customers(id),
             invoice_date TIMESTAMP,
             due_date TIMESTAMP,
             total_amount NUMERIC,
@@ -46,70 +130,298 @@ async fn create_invoices_table(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<(),
     Ok(())
 }
 
+async fn create_customers_table(pool: &sqlx::Pool<sqlx::Postgres>) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS customers (
+            id SERIAL PRIMARY KEY,
+            name TEXT,
+            email TEXT,
+            created_at TIMESTAMP
+        )
+        "#
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Ensures `customers` holds at least `count` rows, topping up with fake ones if it holds
+/// fewer (e.g. a prior run used a smaller `--customers`), then returns every existing
+/// customer id, ordered by id, so invoice generation can sample real foreign keys instead of
+/// a random, possibly-nonexistent, `customer_id`. Draws names and emails from `rng` so that,
+/// for a fixed seed, the same customer rows come out on every run — which also depends on the
+/// returned ids being in a stable order, since `choose()` indexes into them by position.
+async fn populate_customers(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    count: i32,
+    created_at: NaiveDateTime,
+    rng: &mut StdRng,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let existing = sqlx::query_scalar!("SELECT COUNT(*) FROM customers")
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(0);
+
+    let to_insert = count as i64 - existing;
+    if to_insert > 0 {
+        let names: Vec<String> = (0..to_insert).map(|_| Name().fake_with_rng(rng)).collect();
+        let emails: Vec<String> = (0..to_insert).map(|_| SafeEmail().fake_with_rng(rng)).collect();
+        let created_ats: Vec<NaiveDateTime> = vec![created_at; to_insert as usize];
+
+        sqlx::query!(
+            r#"
+            INSERT INTO customers (name, email, created_at)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::timestamp[])
+            "#,
+            &names,
+            &emails,
+            &created_ats,
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    let ids = sqlx::query_scalar!("SELECT id FROM customers ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+
+    assert!(
+        ids.len() as i64 >= count as i64,
+        "customers table has {} rows after populating for a requested {}",
+        ids.len(),
+        count
+    );
+
+    Ok(ids)
+}
+
+/// Parses `--mode <unnest|copy|values>` out of the raw CLI args, defaulting to `unnest`.
+fn parse_mode(args: &[String]) -> InsertMode {
+    args.iter()
+        .position(|a| a == "--mode")
+        .and_then(|i| args.get(i + 1))
+        .map(|raw| InsertMode::parse(raw))
+        .unwrap_or(InsertMode::Unnest)
+}
+
+/// Parses `--workers <n>` out of the raw CLI args, defaulting to the pool size.
+fn parse_workers(args: &[String]) -> usize {
+    let workers = args
+        .iter()
+        .position(|a| a == "--workers")
+        .and_then(|i| args.get(i + 1))
+        .map(|raw| raw.parse().expect("--workers must be an integer"))
+        .unwrap_or(DEFAULT_POOL_SIZE as usize);
+
+    assert!(workers > 0, "--workers must be at least 1");
+    workers
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     dotenv().ok();
     let database_url = env::var("DATABASE_URL")?;
+    let tls = TlsConfig::from_env();
     let pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
+        .max_connections(DEFAULT_POOL_SIZE)
+        .connect_with(tls::pg_connect_options(&database_url, &tls)?)
         .await?;
 
+    create_customers_table(&pool).await?;
     create_invoices_table(&pool).await?;
 
-    let mut rng = rand::thread_rng();
-    let statuses = vec!["Paid", "Pending", "Overdue"];
-    let start_time = Instant::now();
+    let args: Vec<String> = env::args().collect();
+    let gen_config = GenConfig::load(&args)?;
+    let mode = parse_mode(&args);
+    let num_workers = parse_workers(&args);
+
+    // A bare positional arg (as in earlier versions of this tool) overrides the config's
+    // invoice count; this keeps `cargo run -- 50000` working alongside `--config gen.toml`.
+    let num_invoices: i32 = match args.get(1).filter(|a| !a.starts_with("--")) {
+        Some(raw) => raw.parse().expect("The invoice count must be an integer"),
+        None => gen_config.num_invoices,
+    };
+
+    let mut customer_rng = StdRng::seed_from_u64(gen_config.seed);
+    let customers_created_at = gen_config
+        .invoice_date_start
+        .and_hms_opt(0, 0, 0)
+        .expect("Invalid invoice_date_start");
+    let customer_ids = Arc::new(
+        populate_customers(&pool, gen_config.customer_count, customers_created_at, &mut customer_rng).await?,
+    );
 
     let batch_size = 10000;
-    let mut invoices = Vec::with_capacity(batch_size);
+    let start_time = Instant::now();
 
-    let start_date = NaiveDate::from_ymd_opt(2021, 1, 1).expect("Invalid start date");
-    let end_date = NaiveDate::from_ymd_opt(2024, 6, 16).expect("Invalid end date");
+    let (tx, rx) = mpsc::channel::<Vec<Invoice>>(num_workers * 2);
+    let rx = Arc::new(Mutex::new(rx));
 
-    let args: Vec<String> = env::args().collect();
-    let num_invoices: i32 = args.get(1).expect("Please provide the number of invoices to create as a command line argument").parse().expect("The provided argument must be an integer");
-
-    for _ in 0..num_invoices {
-        let invoice_date = random_date_in_range(&mut rng, start_date, end_date)
-            .and_hms_opt(0, 0, 0)
-            .expect("Invalid time");
-        let due_date = invoice_date + Duration::days(rng.gen_range(0..=90));
-
-        let total_amount = BigDecimal::from_str(&format!("{:.2}", rng.gen_range(100.0..10000.0)))?;
-        let tax_amount = BigDecimal::from_str(&format!("{:.2}", rng.gen_range(10.0..1000.0)))?;
-
-        let invoice = Invoice {
-            customer_id: rng.gen_range(1..10000),
-            customer_name: Name().fake(),
-            invoice_date,
-            due_date,
-            total_amount,
-            tax_amount,
-            status: statuses.choose(&mut rng).unwrap().to_string(),
+    let mut workers = JoinSet::new();
+    for _ in 0..num_workers {
+        let conn = match mode {
+            InsertMode::Unnest | InsertMode::Values => WorkerConn::Pool(pool.clone()),
+            InsertMode::Copy => WorkerConn::Copy(connect_copy_client(&database_url, &tls).await?),
         };
+        let rx = Arc::clone(&rx);
+        workers.spawn(async move {
+            let mut rows_inserted: i64 = 0;
+            loop {
+                let batch = rx.lock().await.recv().await;
+                let Some(batch) = batch else { break };
+                let batch_len = batch.len() as i64;
+                insert_batch(mode, &conn, &batch).await?;
+                rows_inserted += batch_len;
+            }
+            Ok::<i64, Box<dyn Error + Send + Sync>>(rows_inserted)
+        });
+    }
+
+    let generator = tokio::spawn(async move {
+        // Offset the customer seed by one so invoice generation doesn't replay the same
+        // pseudorandom stream customer population just consumed.
+        let mut rng = StdRng::seed_from_u64(gen_config.seed.wrapping_add(1));
+        let mut invoices = Vec::with_capacity(batch_size);
+
+        for _ in 0..num_invoices {
+            let invoice_date = random_date_in_range(&mut rng, gen_config.invoice_date_start, gen_config.invoice_date_end)
+                .and_hms_opt(0, 0, 0)
+                .expect("Invalid time");
+            let due_date = invoice_date
+                + Duration::days(rng.gen_range(gen_config.due_date_offset_min..=gen_config.due_date_offset_max));
 
-        invoices.push(invoice);
+            let total_amount = BigDecimal::from_str(&format!("{:.2}", rng.gen_range(gen_config.amount_min..gen_config.amount_max))).expect("valid decimal");
+            let tax_amount = BigDecimal::from_str(&format!("{:.2}", rng.gen_range(gen_config.tax_min..gen_config.tax_max))).expect("valid decimal");
 
-        if invoices.len() == batch_size {
-            insert_invoices(&pool, &invoices).await?;
-            invoices.clear();
+            let invoice = Invoice {
+                customer_id: *customer_ids.choose(&mut rng).expect("customers table must not be empty"),
+                invoice_date,
+                due_date,
+                total_amount,
+                tax_amount,
+                status: gen_config
+                    .statuses
+                    .choose_weighted(&mut rng, |sw| sw.weight)
+                    .expect("statuses must not be empty")
+                    .status
+                    .clone(),
+            };
+
+            invoices.push(invoice);
+
+            if invoices.len() == batch_size {
+                let batch = std::mem::replace(&mut invoices, Vec::with_capacity(batch_size));
+                if tx.send(batch).await.is_err() {
+                    break;
+                }
+            }
         }
-    }
 
-    if !invoices.is_empty() {
-        insert_invoices(&pool, &invoices).await?;
+        if !invoices.is_empty() {
+            let _ = tx.send(invoices).await;
+        }
+        // tx is dropped here, which lets workers exit their receive loop.
+    });
+
+    generator.await?;
+
+    let mut total_inserted: i64 = 0;
+    let mut worker_errors = Vec::new();
+    while let Some(result) = workers.join_next().await {
+        match result {
+            Ok(Ok(rows)) => total_inserted += rows,
+            Ok(Err(e)) => worker_errors.push(e.to_string()),
+            Err(e) => worker_errors.push(e.to_string()),
+        }
     }
 
     let duration = start_time.elapsed();
-    println!("Inserted {} invoices in: {:?}", num_invoices, duration);
+    println!(
+        "Inserted {} invoices in: {:?} (mode: {}, workers: {})",
+        total_inserted,
+        duration,
+        mode.as_str(),
+        num_workers
+    );
+    if !worker_errors.is_empty() {
+        eprintln!("{} worker(s) reported errors:", worker_errors.len());
+        for err in &worker_errors {
+            eprintln!("  - {}", err);
+        }
+        return Err(format!("{} worker(s) failed to insert rows", worker_errors.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Builds `INSERT ... VALUES ($1,$2,...),($n+1,$n+2,...),...` for `rows` rows of
+/// `args_per_row` columns each. The first row's placeholders carry an explicit `::type` cast
+/// (from `types`) so Postgres can infer parameter types without `sqlx::query!`'s compile-time
+/// introspection, letting `sqlx::query` bind plain positional arguments at runtime instead.
+fn multiline_query(prefix: &str, args_per_row: usize, rows: usize, types: &[&str]) -> String {
+    assert_eq!(args_per_row, types.len(), "types must have one entry per column");
+    assert!(rows > 0, "multiline_query requires at least one row");
+
+    let mut sql = String::from(prefix);
+    sql.push(' ');
+
+    for row in 0..rows {
+        if row > 0 {
+            sql.push(',');
+        }
+        sql.push('(');
+        for (col, column_type) in types.iter().enumerate() {
+            if col > 0 {
+                sql.push(',');
+            }
+            sql.push_str(&format!("${}", row * args_per_row + col + 1));
+            if row == 0 {
+                sql.push_str("::");
+                sql.push_str(column_type);
+            }
+        }
+        sql.push(')');
+    }
+
+    sql
+}
+
+/// Inserts `invoices` via a runtime-built multi-row `VALUES` list (see `multiline_query`)
+/// instead of `sqlx::query!`'s `UNNEST` path.
+async fn insert_invoices_values(pool: &sqlx::Pool<sqlx::Postgres>, invoices: &[Invoice]) -> Result<(), sqlx::Error> {
+    if invoices.is_empty() {
+        return Ok(());
+    }
+
+    const COLUMN_TYPES: [&str; 6] = ["int4", "timestamp", "timestamp", "numeric", "numeric", "text"];
+
+    let sql = multiline_query(
+        "INSERT INTO invoices (customer_id, invoice_date, due_date, total_amount, tax_amount, status) VALUES",
+        COLUMN_TYPES.len(),
+        invoices.len(),
+        &COLUMN_TYPES,
+    );
+
+    let mut query = sqlx::query(&sql);
+    for invoice in invoices {
+        query = query
+            .bind(invoice.customer_id)
+            .bind(invoice.invoice_date)
+            .bind(invoice.due_date)
+            .bind(invoice.total_amount.clone())
+            .bind(invoice.tax_amount.clone())
+            .bind(invoice.status.clone());
+    }
+
+    query.execute(pool).await?;
 
     Ok(())
 }
 
 async fn insert_invoices(pool: &sqlx::Pool<sqlx::Postgres>, invoices: &[Invoice]) -> Result<(), sqlx::Error> {
     let customer_ids: Vec<i32> = invoices.iter().map(|i| i.customer_id).collect();
-    let customer_names: Vec<String> = invoices.iter().map(|i| i.customer_name.clone()).collect();
     let invoice_dates: Vec<NaiveDateTime> = invoices.iter().map(|i| i.invoice_date).collect();
     let due_dates: Vec<NaiveDateTime> = invoices.iter().map(|i| i.due_date).collect();
     let total_amounts: Vec<BigDecimal> = invoices.iter().map(|i| i.total_amount.clone()).collect();
@@ -118,19 +430,17 @@ async fn insert_invoices(pool: &sqlx::Pool<sqlx::Postgres>, invoices: &[Invoice]
 
     sqlx::query!(
         r#"
-        INSERT INTO invoices (customer_id, customer_name, invoice_date, due_date, total_amount, tax_amount, status)
+        INSERT INTO invoices (customer_id, invoice_date, due_date, total_amount, tax_amount, status)
         SELECT * FROM UNNEST(
             $1::int4[],
-            $2::text[],
+            $2::timestamp[],
             $3::timestamp[],
-            $4::timestamp[],
+            $4::numeric[],
             $5::numeric[],
-            $6::numeric[],
-            $7::text[]
+            $6::text[]
         )
         "#,
         &customer_ids,
-        &customer_names,
         &invoice_dates,
         &due_dates,
         &total_amounts,
@@ -142,3 +452,80 @@ async fn insert_invoices(pool: &sqlx::Pool<sqlx::Postgres>, invoices: &[Invoice]
 
     Ok(())
 }
+
+/// Streams `invoices` into Postgres via `COPY ... FROM STDIN (FORMAT binary)`, which
+/// avoids building the large per-column parameter vectors `insert_invoices` needs and is
+/// several times faster for large row counts.
+async fn insert_invoices_copy(client: &tokio_postgres::Client, invoices: &[Invoice]) -> Result<(), tokio_postgres::Error> {
+    let column_types = [
+        Type::INT4,
+        Type::TIMESTAMP,
+        Type::TIMESTAMP,
+        Type::NUMERIC,
+        Type::NUMERIC,
+        Type::TEXT,
+    ];
+
+    let sink = client
+        .copy_in(
+            "COPY invoices (customer_id, invoice_date, due_date, total_amount, tax_amount, status) FROM STDIN (FORMAT binary)",
+        )
+        .await?;
+    let writer = tokio_postgres::binary_copy::BinaryCopyInWriter::new(sink, &column_types);
+    tokio::pin!(writer);
+
+    for invoice in invoices {
+        // tokio-postgres's NUMERIC encoding is implemented for rust_decimal::Decimal, not
+        // bigdecimal::BigDecimal, so we convert at the binary-protocol boundary only.
+        let total_amount = bigdecimal_to_rust_decimal(&invoice.total_amount);
+        let tax_amount = bigdecimal_to_rust_decimal(&invoice.tax_amount);
+        writer
+            .as_mut()
+            .write(&[
+                &invoice.customer_id,
+                &invoice.invoice_date,
+                &invoice.due_date,
+                &total_amount,
+                &tax_amount,
+                &invoice.status,
+            ])
+            .await?;
+    }
+
+    writer.finish().await?;
+
+    Ok(())
+}
+
+fn bigdecimal_to_rust_decimal(value: &BigDecimal) -> rust_decimal::Decimal {
+    rust_decimal::Decimal::from_str(&value.to_string()).expect("BigDecimal values stay within rust_decimal's range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::multiline_query;
+
+    #[test]
+    fn casts_types_on_first_row_only() {
+        let sql = multiline_query("INSERT INTO t (a,b) VALUES", 2, 2, &["int4", "text"]);
+        assert_eq!(sql, "INSERT INTO t (a,b) VALUES ($1::int4,$2::text),($3,$4)");
+    }
+
+    #[test]
+    fn numbers_placeholders_sequentially_across_rows() {
+        let sql = multiline_query("INSERT INTO t (a) VALUES", 1, 3, &["int4"]);
+        assert_eq!(sql, "INSERT INTO t (a) VALUES ($1::int4),($2),($3)");
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one row")]
+    fn panics_on_zero_rows() {
+        multiline_query("INSERT INTO t (a) VALUES", 1, 0, &["int4"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "one entry per column")]
+    fn panics_on_mismatched_types_len() {
+        multiline_query("INSERT INTO t (a,b) VALUES", 2, 1, &["int4"]);
+    }
+}
@@ -0,0 +1,119 @@
+use native_tls::{Certificate, Identity, TlsConnector};
+use postgres_native_tls::MakeTlsConnector;
+use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::error::Error;
+use std::fs;
+use std::str::FromStr;
+
+/// How strictly a connection should require SSL, mirroring libpq's `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+}
+
+impl SslMode {
+    /// Maps to the equivalent `PgSslMode` for sqlx's connection path. Unlike tokio-postgres,
+    /// where `native-tls` verifies the certificate against any root added via
+    /// `add_root_certificate`, sqlx's own `Require` never validates the certificate — only
+    /// `VerifyCa`/`VerifyFull` do — so `Require` is promoted to `VerifyFull` whenever a CA
+    /// certificate is configured, to keep both connection paths equally "verified" under the
+    /// same `USE_SSL=require` setting.
+    fn to_pg_ssl_mode(self, has_ca_cert: bool) -> PgSslMode {
+        match self {
+            SslMode::Disable => PgSslMode::Disable,
+            SslMode::Prefer => PgSslMode::Prefer,
+            SslMode::Require if has_ca_cert => PgSslMode::VerifyFull,
+            SslMode::Require => PgSslMode::Require,
+        }
+    }
+}
+
+/// TLS settings read from the environment: whether to use SSL at all, and where to find the
+/// CA certificate and client identity used to authenticate against managed Postgres providers
+/// that require verified SSL.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub mode: SslMode,
+    pub ca_cert_path: Option<String>,
+    pub client_key_path: Option<String>,
+    pub client_cert_path: Option<String>,
+}
+
+impl TlsConfig {
+    /// Reads `USE_SSL` (`disable`/`prefer`/`require`, default `disable` when unset),
+    /// `CA_CERT_PATH`, `CLIENT_KEY_PATH`, and `CLIENT_CERT_PATH` from the environment. Panics
+    /// on an unrecognized `USE_SSL` value rather than silently disabling TLS, since a typo'd
+    /// value (e.g. `requre`) should fail loudly, not drop encryption against a managed DB.
+    pub fn from_env() -> Self {
+        let mode = match std::env::var("USE_SSL") {
+            Ok(raw) => match raw.as_str() {
+                "disable" => SslMode::Disable,
+                "prefer" => SslMode::Prefer,
+                "require" => SslMode::Require,
+                other => panic!("Unknown USE_SSL value '{}'; expected 'disable', 'prefer', or 'require'", other),
+            },
+            Err(_) => SslMode::Disable,
+        };
+
+        TlsConfig {
+            mode,
+            ca_cert_path: std::env::var("CA_CERT_PATH").ok(),
+            client_key_path: std::env::var("CLIENT_KEY_PATH").ok(),
+            client_cert_path: std::env::var("CLIENT_CERT_PATH").ok(),
+        }
+    }
+}
+
+/// Builds the sqlx connect options for `database_url` with this config's SSL mode and
+/// certificates applied, for use with `PgPoolOptions::connect_with`.
+pub fn pg_connect_options(database_url: &str, tls: &TlsConfig) -> Result<PgConnectOptions, Box<dyn Error + Send + Sync>> {
+    let mut options = PgConnectOptions::from_str(database_url)?
+        .ssl_mode(tls.mode.to_pg_ssl_mode(tls.ca_cert_path.is_some()));
+
+    if let Some(ca_path) = &tls.ca_cert_path {
+        options = options.ssl_root_cert(ca_path);
+    }
+    if let Some(cert_path) = &tls.client_cert_path {
+        options = options.ssl_client_cert(cert_path);
+    }
+    if let Some(key_path) = &tls.client_key_path {
+        options = options.ssl_client_key(key_path);
+    }
+
+    Ok(options)
+}
+
+/// Builds the tokio-postgres TLS connector implied by `tls`, loading the CA certificate and
+/// client identity from disk when SSL is enabled. Returns `None` when SSL is disabled, in
+/// which case callers should connect with `NoTls`.
+pub fn tokio_postgres_connector(tls: &TlsConfig) -> Result<Option<MakeTlsConnector>, Box<dyn Error + Send + Sync>> {
+    if tls.mode == SslMode::Disable {
+        return Ok(None);
+    }
+
+    let mut builder = TlsConnector::builder();
+
+    if let Some(ca_path) = &tls.ca_cert_path {
+        let ca_bytes = fs::read(ca_path)?;
+        let ca_cert = Certificate::from_pem(&ca_bytes).or_else(|_| Certificate::from_der(&ca_bytes))?;
+        builder.add_root_certificate(ca_cert);
+    }
+
+    if let (Some(cert_path), Some(key_path)) = (&tls.client_cert_path, &tls.client_key_path) {
+        let cert_bytes = fs::read(cert_path)?;
+        let key_bytes = fs::read(key_path)?;
+        let identity = Identity::from_pkcs8(&cert_bytes, &key_bytes)?;
+        builder.identity(identity);
+    }
+
+    if tls.mode == SslMode::Prefer {
+        // tokio-postgres has no libpq-style "try SSL, fall back to plaintext" negotiation, so
+        // `prefer` instead means "use SSL but don't fail the whole run over an unverifiable cert".
+        builder.danger_accept_invalid_certs(true);
+    }
+
+    let connector = builder.build()?;
+    Ok(Some(MakeTlsConnector::new(connector)))
+}